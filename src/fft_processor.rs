@@ -5,9 +5,12 @@ use num_complex::Complex32;
 
 pub struct FFTProcessor {
     fft_size: usize,
+    hop_size: usize,
     sample_rate: f32,
     buffer: Vec<f32>,
     buffer_position: usize,
+    samples_buffered: usize,
+    samples_since_hop: usize,
     window: Vec<f32>,
     fft: Option<Arc<dyn RealToComplex<f32>>>,
     spectrum: Vec<f32>,
@@ -20,47 +23,64 @@ impl FFTProcessor {
         for i in 0..fft_size {
             window[i] = 0.5 * (1.0 - (2.0 * PI * i as f32 / fft_size as f32).cos());
         }
-        
+
         Self {
             fft_size,
+            hop_size: fft_size, // No overlap until set_hop_size is called
             sample_rate: 44100.0,  // Default, will be set in initialize
             buffer: vec![0.0; fft_size],
             buffer_position: 0,
+            samples_buffered: 0,
+            samples_since_hop: 0,
             window,
             fft: None,
             spectrum: vec![0.0; fft_size / 2],
         }
     }
-    
+
     pub fn initialize(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
-        
+
         // Initialize FFT
         let mut planner = RealFftPlanner::<f32>::new();
         self.fft = Some(planner.plan_fft_forward(self.fft_size));
-        
+
         // Reset buffers
         self.reset();
     }
-    
+
     pub fn reset(&mut self) {
         self.buffer.fill(0.0);
         self.buffer_position = 0;
+        self.samples_buffered = 0;
+        self.samples_since_hop = 0;
         self.spectrum.fill(0.0);
     }
-    
+
+    /// Sets the hop size: how many samples advance between analysis frames.
+    /// `hop_size == fft_size` (the default) reproduces the old non-overlapping
+    /// behavior; smaller values give overlapping frames and lower latency.
+    pub fn set_hop_size(&mut self, hop_size: usize) {
+        self.hop_size = hop_size.clamp(1, self.fft_size);
+    }
+
     pub fn process_sample(&mut self, sample: f32) {
-        // Add the sample to our buffer
+        // Add the sample to our ring buffer
         self.buffer[self.buffer_position] = sample;
         self.buffer_position = (self.buffer_position + 1) % self.fft_size;
+        self.samples_buffered = (self.samples_buffered + 1).min(self.fft_size);
+        self.samples_since_hop += 1;
     }
-    
+
     pub fn is_frame_complete(&self) -> bool {
-        // Check if we've collected a full buffer
-        self.buffer_position == 0
+        // A frame is ready once the ring buffer holds a full window and
+        // we've advanced a full hop since the last analysis.
+        self.samples_buffered >= self.fft_size && self.samples_since_hop >= self.hop_size
     }
-    
+
     pub fn compute_spectrum(&mut self) -> Vec<f32> {
+        self.samples_since_hop = 0;
+
         if let Some(fft) = &self.fft {
             // Apply window function
             let mut windowed_buffer = vec![0.0; self.fft_size];
@@ -90,4 +110,116 @@ impl FFTProcessor {
     pub fn get_frequency_for_bin(&self, bin: usize) -> f32 {
         bin as f32 * self.sample_rate / self.fft_size as f32
     }
+
+    /// Parabolically interpolates the true spectral peak frequency nearest
+    /// `expected_freq`, searching a small window of bins around it. Gives a
+    /// far more precise fundamental than the raw per-bin resolution allows.
+    pub fn refine_peak_frequency(&self, spectrum: &[f32], expected_freq: f32) -> f32 {
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let search_radius = 2usize;
+        let max_bin = spectrum.len().saturating_sub(2).max(1);
+        let center_bin = ((expected_freq / bin_hz).round() as usize).clamp(1, max_bin);
+
+        let lo = center_bin.saturating_sub(search_radius).max(1);
+        let hi = (center_bin + search_radius).min(max_bin);
+
+        let mut peak_bin = center_bin;
+        let mut peak_mag = spectrum[peak_bin];
+        for bin in lo..=hi {
+            if spectrum[bin] > peak_mag {
+                peak_mag = spectrum[bin];
+                peak_bin = bin;
+            }
+        }
+
+        // Parabolic interpolation across the peak and its two neighbors.
+        let s0 = spectrum[peak_bin - 1];
+        let s1 = spectrum[peak_bin];
+        let s2 = spectrum[peak_bin + 1];
+        let denom = s0 - 2.0 * s1 + s2;
+        let offset = if denom.abs() > f32::EPSILON {
+            0.5 * (s0 - s2) / denom
+        } else {
+            0.0
+        };
+
+        (peak_bin as f32 + offset) * bin_hz
+    }
+
+    /// Returns the most recent `fft_size` samples in chronological order
+    /// (oldest first), unwindowed, for time-domain analysis like YIN.
+    pub fn time_domain_buffer(&self) -> Vec<f32> {
+        let mut samples = vec![0.0; self.fft_size];
+        for i in 0..self.fft_size {
+            let buffer_idx = (self.buffer_position + i) % self.fft_size;
+            samples[i] = self.buffer[buffer_idx];
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drives a processor sample-by-sample the way `process()` does: check
+    /// `is_frame_complete` after every sample, not once after a whole block.
+    /// `compute_spectrum` resets the hop counter, so each sample yields at
+    /// most one frame.
+    fn frames_completed_per_sample(processor: &mut FFTProcessor, num_samples: usize) -> usize {
+        let mut frames = 0;
+        for _ in 0..num_samples {
+            processor.process_sample(0.0);
+            while processor.is_frame_complete() {
+                processor.compute_spectrum();
+                frames += 1;
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn non_overlapping_hop_emits_one_frame_per_fft_size_samples() {
+        let mut processor = FFTProcessor::new(1024);
+        // Default hop_size == fft_size until set_hop_size is called.
+        assert_eq!(frames_completed_per_sample(&mut processor, 1024), 1);
+        assert_eq!(frames_completed_per_sample(&mut processor, 1024), 1);
+    }
+
+    #[test]
+    fn overlapping_hop_emits_a_frame_every_hop_even_within_one_block() {
+        let mut processor = FFTProcessor::new(4096);
+        processor.set_hop_size(1024);
+
+        // First 4096 samples just fill the window for the first frame.
+        assert_eq!(frames_completed_per_sample(&mut processor, 4096), 1);
+
+        // A single 1024-sample block afterward should cross exactly one more
+        // hop boundary, not silently drop it.
+        assert_eq!(frames_completed_per_sample(&mut processor, 1024), 1);
+
+        // A block spanning four hops should yield four frames, not one -
+        // this is the bug a single `if is_frame_complete()` after the whole
+        // per-sample loop would miss.
+        assert_eq!(frames_completed_per_sample(&mut processor, 4096), 4);
+    }
+
+    #[test]
+    fn checking_completion_only_once_per_block_drops_frames() {
+        // Demonstrates the bug a per-block (rather than per-sample) check
+        // produces: multiple hops crossed in one block collapse to one frame.
+        let mut processor = FFTProcessor::new(4096);
+        processor.set_hop_size(1024);
+        frames_completed_per_sample(&mut processor, 4096); // warm up the window
+
+        for _ in 0..4096 {
+            processor.process_sample(0.0);
+        }
+        let mut frames = 0;
+        if processor.is_frame_complete() {
+            processor.compute_spectrum();
+            frames += 1;
+        }
+        assert_eq!(frames, 1, "a single post-loop check can only ever see one frame, even though 4 hops were crossed");
+    }
 }
@@ -9,7 +9,10 @@ mod fft_processor;
 mod spectral_analysis;
 mod note_detection;
 mod midi_output;
+mod midi_recorder;
+mod quantize;
 mod utils;
+mod velocity;
 mod ui;
 
 // Main plugin struct
@@ -26,23 +29,63 @@ pub struct GuitarMidiTracker {
     
     // Tracking state
     note_detector: note_detection::NoteDetector,
-    
+    note_output_state: midi_output::NoteOutputState,
+    velocity_tracker: velocity::VelocityTracker,
+
+    // MIDI recording state
+    midi_recorder: midi_recorder::MidiRecorder,
+    sample_position: u64,
+    save_recording_prev: bool,
+    prev_detection_mode: DetectionMode,
+
     // Visualization data for UI
     fft_magnitude_buffer: Vec<f32>,
-    detected_notes: Vec<u8>,
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionMode {
+    /// Cosine-similarity matching against learned spectra. Polyphonic, but
+    /// needs a learning pass first.
+    Spectral,
+    /// Time-domain YIN pitch detection. Monophonic and training-free.
+    Yin,
 }
 
 #[derive(Params)]
 struct GuitarMidiTrackerParams {
     #[id = "input_gain"]
     pub input_gain: FloatParam,
-    
+
     #[id = "sensitivity"]
     pub sensitivity: FloatParam,
-    
+
     #[id = "max_notes"]
     pub max_polyphony: IntParam,
-    
+
+    #[id = "hop_size"]
+    pub hop_size: IntParam,
+
+    #[id = "detection_mode"]
+    pub detection_mode: EnumParam<DetectionMode>,
+
+    #[id = "pitch_bend_range"]
+    pub pitch_bend_range: FloatParam,
+
+    #[id = "quantize_root"]
+    pub quantize_root: IntParam,
+
+    #[id = "quantize_scale"]
+    pub quantize_scale: EnumParam<quantize::Scale>,
+
+    #[id = "velocity_floor_db"]
+    pub velocity_floor_db: FloatParam,
+
+    #[id = "velocity_ceiling_db"]
+    pub velocity_ceiling_db: FloatParam,
+
+    #[id = "velocity_attack"]
+    pub velocity_attack: FloatParam,
+
     #[id = "learning_mode"]
     pub learning_mode: BoolParam,
     
@@ -51,10 +94,19 @@ struct GuitarMidiTrackerParams {
     
     #[id = "save_learned_data"]
     pub save_learned_data: BoolParam,
-    
+
     #[id = "load_learned_data"]
     pub load_learned_data: BoolParam,
-    
+
+    #[id = "record_enabled"]
+    pub record_enabled: BoolParam,
+
+    #[id = "recording_bpm"]
+    pub recording_bpm: FloatParam,
+
+    #[id = "save_recording"]
+    pub save_recording: BoolParam,
+
     #[persist = "editor_state"]
     editor_state: Arc<parking_lot::RwLock<ui::EditorState>>,
 }
@@ -81,7 +133,52 @@ impl Default for GuitarMidiTrackerParams {
                 6,
                 IntRange::Linear { min: 1, max: 12 }
             ),
-            
+
+            hop_size: IntParam::new(
+                "Hop Size",
+                4096, // No overlap by default, matching the old fixed-buffer behavior
+                IntRange::Linear { min: 256, max: 4096 }
+            )
+            .with_unit(" samples"),
+
+            detection_mode: EnumParam::new("Detection Mode", DetectionMode::Spectral),
+
+            pitch_bend_range: FloatParam::new(
+                "Pitch Bend Range",
+                2.0,
+                FloatRange::Linear { min: 0.5, max: 12.0 }
+            )
+            .with_unit(" st"),
+
+            quantize_root: IntParam::new(
+                "Quantize Root",
+                0, // C
+                IntRange::Linear { min: 0, max: 11 }
+            )
+            .with_value_to_string(Arc::new(|value| utils::NOTE_NAMES[value as usize].to_string())),
+
+            quantize_scale: EnumParam::new("Quantize Scale", quantize::Scale::Chromatic),
+
+            velocity_floor_db: FloatParam::new(
+                "Velocity Floor",
+                -50.0,
+                FloatRange::Linear { min: -80.0, max: -20.0 }
+            )
+            .with_unit(" dB"),
+
+            velocity_ceiling_db: FloatParam::new(
+                "Velocity Ceiling",
+                0.0,
+                FloatRange::Linear { min: -20.0, max: 0.0 }
+            )
+            .with_unit(" dB"),
+
+            velocity_attack: FloatParam::new(
+                "Velocity Attack",
+                0.3,
+                FloatRange::Linear { min: 0.01, max: 1.0 }
+            ),
+
             learning_mode: BoolParam::new("Learning Mode", false),
             
             learning_note: FloatParam::new(
@@ -122,6 +219,31 @@ impl Default for GuitarMidiTrackerParams {
                     })
                 }),
                 
+            record_enabled: BoolParam::new("Record", false),
+
+            recording_bpm: FloatParam::new(
+                "Recording Tempo",
+                120.0,
+                FloatRange::Linear { min: 40.0, max: 240.0 }
+            )
+            .with_unit(" BPM"),
+
+            save_recording: BoolParam::new("Save Recording", false)
+                .with_callback({
+                    let save_trigger = Arc::new(AtomicBool::new(false));
+                    let save_trigger_clone = save_trigger.clone();
+
+                    Arc::new(move |value| {
+                        if value {
+                            // Only logs; process() writes the file and resets
+                            // this parameter
+                            println!("Saving MIDI recording...");
+                            // Clear this callback's local trigger flag
+                            save_trigger_clone.store(false, Ordering::Relaxed);
+                        }
+                    })
+                }),
+
             editor_state: Arc::new(parking_lot::RwLock::new(ui::EditorState::default())),
         }
     }
@@ -136,8 +258,13 @@ impl Default for GuitarMidiTracker {
             learning_mode: AtomicBool::new(false),
             current_learning_note: AtomicF32::new(60.0), // Middle C
             note_detector: note_detection::NoteDetector::new(),
+            note_output_state: midi_output::NoteOutputState::default(),
+            velocity_tracker: velocity::VelocityTracker::new(),
+            midi_recorder: midi_recorder::MidiRecorder::new(),
+            sample_position: 0,
+            save_recording_prev: false,
+            prev_detection_mode: DetectionMode::Spectral,
             fft_magnitude_buffer: Vec::new(),
-            detected_notes: Vec::new(),
         }
     }
 }
@@ -193,6 +320,8 @@ impl Plugin for GuitarMidiTracker {
     fn reset(&mut self) {
         self.fft_processor.reset();
         self.note_detector.reset();
+        self.note_output_state = midi_output::NoteOutputState::default();
+        self.velocity_tracker.reset();
     }
 
     fn process(
@@ -205,10 +334,38 @@ impl Plugin for GuitarMidiTracker {
         let num_samples = buffer.samples();
         let num_channels = buffer.channels();
         
+        // Pick up any hop size change from the host before analyzing frames
+        self.fft_processor.set_hop_size(self.params.hop_size.value() as usize);
+
+        // Spectral energy and YIN RMS live on completely different scales, so
+        // a running peak calibrated to one pins velocities near 0 or 1 if the
+        // mode is switched mid-session. Reset the tracker whenever it changes.
+        let detection_mode = self.params.detection_mode.value();
+        if detection_mode != self.prev_detection_mode {
+            self.velocity_tracker.reset();
+            self.prev_detection_mode = detection_mode;
+        }
+
         // Check if we should be in learning mode
         let learning_mode = self.params.learning_mode.value();
         self.learning_mode.store(learning_mode, Ordering::Relaxed);
-        
+
+        // Arm/disarm the MIDI recorder and save a take when requested. The
+        // "Save Recording" button is a one-shot trigger, not a toggle, so we
+        // have to reset it back to false ourselves once the take is saved -
+        // otherwise every click after the first one is a no-op, since the
+        // param is already sitting at `true`.
+        self.midi_recorder.set_armed(self.params.record_enabled.value());
+        let save_requested = self.params.save_recording.value();
+        if save_requested && !self.save_recording_prev {
+            let bpm = self.params.recording_bpm.value();
+            if let Err(err) = self.midi_recorder.save("recording.mid", self.sample_rate, bpm) {
+                println!("Failed to save MIDI recording: {}", err);
+            }
+            self.params.save_recording.set_plain_value(false);
+        }
+        self.save_recording_prev = save_requested;
+
         if learning_mode {
             // Learning mode - analyze individual notes
             let learning_note_midi = self.params.learning_note.value() as u8;
@@ -222,33 +379,42 @@ impl Plugin for GuitarMidiTracker {
                     input_sample += buffer[channel][i];
                 }
                 input_sample /= num_channels.min(2) as f32;
-                
+
                 // Apply input gain
                 let gain = self.params.input_gain.smoothed.next();
                 input_sample *= utils::db_to_gain(gain);
-                
+
                 // Process the sample for learning
                 self.fft_processor.process_sample(input_sample);
-                
+
                 // For passthrough monitoring, copy input to output
                 for channel in 0..buffer.channels() {
                     buffer[channel][i] = input_sample;
                 }
-            }
-            
-            // Check if we have a complete FFT frame
-            if self.fft_processor.is_frame_complete() {
-                let spectrum = self.fft_processor.compute_spectrum();
-                
-                // Update FFT visualization buffer
-                self.fft_magnitude_buffer = spectrum.clone();
-                
-                // Learn the current note
-                let note_midi = self.current_learning_note.load(Ordering::Relaxed) as u8;
-                learning::learn_note(&mut self.note_detector, note_midi, &spectrum);
+
+                // Check for a completed frame after every sample: when the
+                // host block spans multiple hops, each hop boundary must
+                // still produce its own analysis, not just the last one.
+                while self.fft_processor.is_frame_complete() {
+                    let spectrum = self.fft_processor.compute_spectrum();
+
+                    // Update FFT visualization buffer
+                    self.fft_magnitude_buffer = spectrum.clone();
+
+                    // Learn the current note
+                    let note_midi = self.current_learning_note.load(Ordering::Relaxed) as u8;
+                    learning::learn_note(&mut self.note_detector, note_midi, &spectrum);
+                }
             }
         } else {
             // Tracking mode - detect notes from polyphonic input
+            let floor_db = self.params.velocity_floor_db.value();
+            let ceiling_db = self.params.velocity_ceiling_db.value();
+            let attack = self.params.velocity_attack.value();
+            let quantize_root = self.params.quantize_root.value() as u8;
+            let quantize_scale = self.params.quantize_scale.value();
+            let pitch_bend_range = self.params.pitch_bend_range.value();
+
             for i in 0..num_samples {
                 // Get mono input (average channels if stereo)
                 let mut input_sample = 0.0;
@@ -256,39 +422,91 @@ impl Plugin for GuitarMidiTracker {
                     input_sample += buffer[channel][i];
                 }
                 input_sample /= num_channels.min(2) as f32;
-                
+
                 // Apply input gain
                 let gain = self.params.input_gain.smoothed.next();
                 input_sample *= utils::db_to_gain(gain);
-                
+
                 // Process the sample for note detection
                 self.fft_processor.process_sample(input_sample);
-                
+
                 // For passthrough monitoring, copy input to output
                 for channel in 0..buffer.channels() {
                     buffer[channel][i] = input_sample;
                 }
-            }
-            
-            // Check if we have a complete FFT frame
-            if self.fft_processor.is_frame_complete() {
-                let spectrum = self.fft_processor.compute_spectrum();
-                
-                // Update FFT visualization buffer
-                self.fft_magnitude_buffer = spectrum.clone();
-                
-                // Detect notes from the spectrum
-                let max_notes = self.params.max_polyphony.value() as usize;
-                let sensitivity = self.params.sensitivity.value();
-                let detected_notes = self.note_detector.detect_notes(&spectrum, max_notes, sensitivity);
-                
-                // Output MIDI notes
-                midi_output::output_midi_notes(context, &detected_notes, &self.detected_notes);
-                
-                // Update our stored note state
-                self.detected_notes = detected_notes;
+
+                // Check for a completed frame after every sample: when the
+                // host block spans multiple hops, each hop boundary must
+                // still produce its own analysis, not just the last one.
+                while self.fft_processor.is_frame_complete() {
+                    let spectrum = self.fft_processor.compute_spectrum();
+
+                    // Update FFT visualization buffer
+                    self.fft_magnitude_buffer = spectrum.clone();
+
+                    // Detect notes, either polyphonically from the spectrum or
+                    // monophonically via YIN on the raw time-domain buffer, and
+                    // resolve each to a precise fundamental frequency (for bend
+                    // tracking) and a velocity derived from its energy.
+                    let detected_notes: Vec<(f32, f32)> = match self.params.detection_mode.value() {
+                        DetectionMode::Spectral => {
+                            let max_notes = self.params.max_polyphony.value() as usize;
+                            let sensitivity = self.params.sensitivity.value();
+                            self.note_detector
+                                .detect_notes(&spectrum, max_notes, sensitivity)
+                                .into_iter()
+                                .map(|(note, energy)| {
+                                    let frequency = self.fft_processor
+                                        .refine_peak_frequency(&spectrum, utils::midi_note_to_freq(note));
+                                    let velocity = self.velocity_tracker
+                                        .velocity_for(note, energy, floor_db, ceiling_db, attack);
+                                    (frequency, velocity)
+                                })
+                                .collect()
+                        }
+                        DetectionMode::Yin => {
+                            let samples = self.fft_processor.time_domain_buffer();
+                            match self.note_detector.detect_note_yin(&samples) {
+                                Some((note, frequency)) => {
+                                    let energy = (samples.iter().map(|s| s * s).sum::<f32>()
+                                        / samples.len() as f32)
+                                        .sqrt();
+                                    let velocity = self.velocity_tracker
+                                        .velocity_for(note, energy, floor_db, ceiling_db, attack);
+                                    vec![(frequency, velocity)]
+                                }
+                                None => Vec::new(),
+                            }
+                        }
+                    };
+
+                    // Snap each candidate to the selected key/scale, then
+                    // deduplicate notes that collapsed onto the same pitch
+                    let mut detected_notes: Vec<(f32, f32)> = detected_notes
+                        .into_iter()
+                        .map(|(frequency, velocity)| {
+                            (quantize::quantize_frequency(frequency, quantize_root, quantize_scale), velocity)
+                        })
+                        .collect();
+                    detected_notes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                    detected_notes.dedup_by_key(|&mut (frequency, _)| utils::freq_to_midi_note(frequency));
+
+                    // Output MIDI notes, with pitch bend for in-range drift,
+                    // timed at the sample within this block where the hop landed.
+                    midi_output::output_midi_notes(
+                        context,
+                        &detected_notes,
+                        &mut self.note_output_state,
+                        pitch_bend_range,
+                        &mut self.midi_recorder,
+                        self.sample_position + i as u64,
+                        i as u32,
+                    );
+                }
             }
         }
+
+        self.sample_position += num_samples as u64;
         
         ProcessStatus::Normal
     }
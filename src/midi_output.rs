@@ -1,35 +1,154 @@
 use nih_plug::prelude::*;
+use std::collections::HashMap;
 
+/// A detected note only counts as "re-triggered" once its pitch drifts more
+/// than this many cents away from the center it was held at; smaller drifts
+/// are expressed as pitch bend instead, so bends and vibrato don't chatter
+/// into new NoteOns.
+const RETRIGGER_CENTS: f32 = 70.0;
+
+/// Tracks currently-held MIDI notes and the equal-tempered frequency of each,
+/// so pitch bend can be computed relative to a stable per-note center across
+/// frames.
+#[derive(Default)]
+pub struct NoteOutputState {
+    held: HashMap<u8, f32>,
+}
+
+/// Sends NoteOn/NoteOff for newly detected/released pitches and MidiPitchBend
+/// for pitches that are still tracking a held note, so bends and vibrato come
+/// through continuously instead of being quantized away.
 pub fn output_midi_notes<P: Plugin>(
     context: &mut impl ProcessContext<P>,
-    current_notes: &[u8],
-    previous_notes: &[u8],
+    detected_notes: &[(f32, f32)], // (frequency, velocity)
+    state: &mut NoteOutputState,
+    pitch_bend_range_semitones: f32,
+    recorder: &mut crate::midi_recorder::MidiRecorder,
+    sample_position: u64,
+    timing: u32,
 ) {
-    // Find notes that need to be turned off (in previous but not in current)
-    for &note in previous_notes {
-        if !current_notes.contains(&note) {
-            let event = NoteEvent::NoteOff {
-                timing: 0,
-                voice_id: None,
-                channel: 0,
-                note,
-                velocity: 0.0,
-            };
-            context.send_event(event);
+    let mut matched = vec![false; detected_notes.len()];
+
+    // Held notes whose pitch is still within hysteresis range get re-bent
+    // instead of retriggered; everything else gets a NoteOff.
+    for note in state.held.keys().copied().collect::<Vec<_>>() {
+        let center = state.held[&note];
+        let closest = detected_notes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !matched[*i])
+            .min_by(|(_, (a, _)), (_, (b, _))| (*a - center).abs().partial_cmp(&(*b - center).abs()).unwrap());
+
+        match closest {
+            Some((i, &(frequency, _))) if cents_between(frequency, center).abs() < RETRIGGER_CENTS => {
+                matched[i] = true;
+                send_pitch_bend(context, frequency, center, pitch_bend_range_semitones, timing);
+            }
+            _ => {
+                context.send_event(NoteEvent::NoteOff {
+                    timing,
+                    voice_id: None,
+                    channel: 0,
+                    note,
+                    velocity: 0.0,
+                });
+                recorder.record_note_off(sample_position, 0, note);
+                state.held.remove(&note);
+            }
         }
     }
-    
-    // Find notes that need to be turned on (in current but not in previous)
-    for &note in current_notes {
-        if !previous_notes.contains(&note) {
-            let event = NoteEvent::NoteOn {
-                timing: 0,
-                voice_id: None,
-                channel: 0,
-                note,
-                velocity: 0.8,  // Fixed velocity for now
-            };
-            context.send_event(event);
+
+    // Unmatched detections are new pitches: trigger a fresh NoteOn on the
+    // nearest MIDI note, with velocity carried from spectral energy.
+    for (i, &(frequency, velocity)) in detected_notes.iter().enumerate() {
+        if matched[i] {
+            continue;
+        }
+
+        let note = crate::utils::freq_to_midi_note(frequency);
+        if state.held.contains_key(&note) {
+            continue;
         }
+
+        context.send_event(NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity,
+        });
+        recorder.record_note_on(sample_position, 0, note, (velocity * 127.0) as u8);
+        // Hold the note's fixed equal-tempered frequency, not the raw
+        // detected one, so bend reflects drift from the triggered semitone
+        // instead of being zero by construction at the trigger frame.
+        state.held.insert(note, crate::utils::midi_note_to_freq(note));
+    }
+}
+
+fn cents_between(frequency: f32, reference: f32) -> f32 {
+    1200.0 * (frequency / reference).log2()
+}
+
+/// Normalizes a semitone offset from center to the 0.0-1.0 range `MidiPitchBend`
+/// expects, clamped to `pitch_bend_range_semitones` in either direction.
+fn normalized_bend(semitones: f32, pitch_bend_range_semitones: f32) -> f32 {
+    0.5 + 0.5 * (semitones / pitch_bend_range_semitones).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cents_between_is_zero_for_same_frequency() {
+        assert!((cents_between(440.0, 440.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn cents_between_one_octave_is_1200() {
+        assert!((cents_between(880.0, 440.0) - 1200.0).abs() < 1e-2);
+        assert!((cents_between(220.0, 440.0) - -1200.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn cents_between_one_semitone_up() {
+        let semitone_up = 440.0 * 2.0_f32.powf(1.0 / 12.0);
+        assert!((cents_between(semitone_up, 440.0) - 100.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn normalized_bend_centers_at_half_for_no_drift() {
+        assert!((normalized_bend(0.0, 2.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalized_bend_clamps_to_full_range_endpoints() {
+        assert!((normalized_bend(2.0, 2.0) - 1.0).abs() < 1e-6);
+        assert!((normalized_bend(-2.0, 2.0) - 0.0).abs() < 1e-6);
+        // Drift beyond the configured range clamps rather than wrapping.
+        assert!((normalized_bend(10.0, 2.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalized_bend_is_linear_within_range() {
+        assert!((normalized_bend(1.0, 2.0) - 0.75).abs() < 1e-6);
+        assert!((normalized_bend(-1.0, 2.0) - 0.25).abs() < 1e-6);
     }
 }
+
+fn send_pitch_bend<P: Plugin>(
+    context: &mut impl ProcessContext<P>,
+    frequency: f32,
+    center: f32,
+    pitch_bend_range_semitones: f32,
+    timing: u32,
+) {
+    let semitones = cents_between(frequency, center) / 100.0;
+    let normalized = normalized_bend(semitones, pitch_bend_range_semitones);
+
+    context.send_event(NoteEvent::MidiPitchBend {
+        timing,
+        channel: 0,
+        value: normalized,
+    });
+}
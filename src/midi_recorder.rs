@@ -0,0 +1,203 @@
+/// Pulses per quarter note used when quantizing recorded samples to ticks.
+const PPQ: u16 = 480;
+
+struct RecordedEvent {
+    sample_position: u64,
+    status: u8,
+    channel: u8,
+    note: u8,
+    velocity: u8,
+}
+
+/// Captures NoteOn/NoteOff events produced by `midi_output::output_midi_notes`
+/// while armed, and exports them as a format-0 Standard MIDI File.
+pub struct MidiRecorder {
+    armed: bool,
+    events: Vec<RecordedEvent>,
+}
+
+impl MidiRecorder {
+    pub fn new() -> Self {
+        Self {
+            armed: false,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_armed(&mut self, armed: bool) {
+        if armed && !self.armed {
+            self.events.clear();
+        }
+        self.armed = armed;
+    }
+
+    pub fn record_note_on(&mut self, sample_position: u64, channel: u8, note: u8, velocity: u8) {
+        if !self.armed {
+            return;
+        }
+        self.events.push(RecordedEvent {
+            sample_position,
+            status: 0x90,
+            channel,
+            note,
+            velocity,
+        });
+    }
+
+    pub fn record_note_off(&mut self, sample_position: u64, channel: u8, note: u8) {
+        if !self.armed {
+            return;
+        }
+        self.events.push(RecordedEvent {
+            sample_position,
+            status: 0x80,
+            channel,
+            note,
+            velocity: 0,
+        });
+    }
+
+    /// Serializes the captured events as a format-0 SMF at the given tempo
+    /// and writes it to `path`.
+    pub fn save(&self, path: &str, sample_rate: f32, bpm: f32) -> std::io::Result<()> {
+        std::fs::write(path, self.to_smf_bytes(sample_rate, bpm))
+    }
+
+    fn to_smf_bytes(&self, sample_rate: f32, bpm: f32) -> Vec<u8> {
+        let ticks_per_sample = (bpm as f64 / 60.0 * PPQ as f64) / sample_rate as f64;
+
+        let mut track = Vec::new();
+
+        // Tempo meta event up front, derived from the BPM param.
+        let micros_per_quarter = (60_000_000.0 / bpm as f64).round() as u32;
+        write_vlq(&mut track, 0);
+        track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+        track.push((micros_per_quarter >> 16) as u8);
+        track.push((micros_per_quarter >> 8) as u8);
+        track.push(micros_per_quarter as u8);
+
+        let mut last_sample = 0u64;
+        for event in &self.events {
+            let delta_samples = event.sample_position.saturating_sub(last_sample);
+            last_sample = event.sample_position;
+            let delta_ticks = (delta_samples as f64 * ticks_per_sample).round() as u32;
+
+            write_vlq(&mut track, delta_ticks);
+            track.push(event.status | (event.channel & 0x0F));
+            track.push(event.note & 0x7F);
+            track.push(event.velocity & 0x7F);
+        }
+
+        write_vlq(&mut track, 0);
+        track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+        let mut smf = Vec::new();
+        smf.extend_from_slice(b"MThd");
+        smf.extend_from_slice(&6u32.to_be_bytes());
+        smf.extend_from_slice(&0u16.to_be_bytes()); // format 0
+        smf.extend_from_slice(&1u16.to_be_bytes()); // 1 track
+        smf.extend_from_slice(&PPQ.to_be_bytes());
+
+        smf.extend_from_slice(b"MTrk");
+        smf.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        smf.extend_from_slice(&track);
+
+        smf
+    }
+}
+
+/// Writes `value` as a MIDI variable-length quantity (big-endian, 7 bits per byte).
+fn write_vlq(buffer: &mut Vec<u8>, value: u32) {
+    let mut stack = vec![(value & 0x7F) as u8];
+    let mut v = value >> 7;
+    while v > 0 {
+        stack.push(((v & 0x7F) as u8) | 0x80);
+        v >>= 7;
+    }
+    buffer.extend(stack.into_iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: u32) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        write_vlq(&mut buffer, value);
+        buffer
+    }
+
+    #[test]
+    fn vlq_single_byte_values() {
+        assert_eq!(vlq(0), vec![0x00]);
+        assert_eq!(vlq(0x40), vec![0x40]);
+        assert_eq!(vlq(0x7F), vec![0x7F]);
+    }
+
+    #[test]
+    fn vlq_multi_byte_values() {
+        // Canonical examples from the SMF spec.
+        assert_eq!(vlq(0x80), vec![0x81, 0x00]);
+        assert_eq!(vlq(0x2000), vec![0xC0, 0x00]);
+        assert_eq!(vlq(0x3FFF), vec![0xFF, 0x7F]);
+        assert_eq!(vlq(0x100000), vec![0xC0, 0x80, 0x00]);
+        assert_eq!(vlq(0x0FFFFFFF), vec![0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn smf_header_describes_a_single_format_0_track() {
+        let recorder = MidiRecorder::new();
+        let bytes = recorder.to_smf_bytes(44100.0, 120.0);
+
+        assert_eq!(&bytes[0..4], b"MThd");
+        assert_eq!(&bytes[4..8], &6u32.to_be_bytes());
+        assert_eq!(&bytes[8..10], &0u16.to_be_bytes()); // format 0
+        assert_eq!(&bytes[10..12], &1u16.to_be_bytes()); // 1 track
+        assert_eq!(&bytes[12..14], &PPQ.to_be_bytes());
+        assert_eq!(&bytes[14..18], b"MTrk");
+    }
+
+    #[test]
+    fn smf_track_contains_tempo_and_end_of_track_events() {
+        let recorder = MidiRecorder::new();
+        let bytes = recorder.to_smf_bytes(44100.0, 120.0);
+        let track = &bytes[22..]; // after MThd (14 bytes) + "MTrk" + length (8 bytes)
+
+        // delta-time 0, tempo meta event for 120 BPM (500,000 us/quarter)
+        assert_eq!(&track[0..4], &[0x00, 0xFF, 0x51, 0x03]);
+        assert_eq!(&track[4..7], &[0x07, 0xA1, 0x20]);
+
+        // end-of-track meta event closes out the data
+        assert_eq!(&track[track.len() - 3..], &[0xFF, 0x2F, 0x00]);
+    }
+
+    #[test]
+    fn recorded_notes_round_trip_into_note_on_off_bytes() {
+        let mut recorder = MidiRecorder::new();
+        recorder.set_armed(true);
+        recorder.record_note_on(0, 0, 69, 100);
+        recorder.record_note_off(44100, 0, 69);
+
+        let bytes = recorder.to_smf_bytes(44100.0, 120.0);
+        let track = &bytes[22..];
+
+        // Tempo event occupies the first 7 bytes; the NoteOn follows at
+        // delta-time 0.
+        assert_eq!(&track[7..11], &[0x00, 0x90, 69, 100]);
+
+        // One second later at 120 BPM / 480 PPQ is 960 ticks (VLQ 0x87 0x40),
+        // followed by the NoteOff.
+        assert_eq!(&track[11..16], &[0x87, 0x40, 0x80, 69, 0]);
+    }
+
+    #[test]
+    fn disarming_and_rearming_discards_previous_take() {
+        let mut recorder = MidiRecorder::new();
+        recorder.set_armed(true);
+        recorder.record_note_on(0, 0, 60, 100);
+        recorder.set_armed(false);
+        recorder.set_armed(true); // rising edge clears old events
+
+        assert!(recorder.events.is_empty());
+    }
+}
@@ -29,32 +29,118 @@ impl NoteDetector {
         notes.insert(note, spectrum.to_vec());
     }
     
-    pub fn detect_notes(&self, spectrum: &[f32], max_notes: usize, sensitivity: f32) -> Vec<u8> {
+    /// Returns the top `max_notes` matches above `sensitivity`, each paired
+    /// with the summed magnitude of its fundamental and first few harmonics
+    /// so callers can derive a velocity from spectral energy.
+    pub fn detect_notes(&self, spectrum: &[f32], max_notes: usize, sensitivity: f32) -> Vec<(u8, f32)> {
         let notes = self.learned_notes.read().unwrap();
         if notes.is_empty() {
             return Vec::new();
         }
-        
+
         // Calculate similarity score for each learned note
         let mut note_scores: Vec<(u8, f32)> = Vec::new();
-        
+
         for (&note, &ref learned_spectrum) in notes.iter() {
             let similarity = self.calculate_similarity(spectrum, learned_spectrum);
             if similarity > sensitivity * 0.5 {  // Threshold based on sensitivity
                 note_scores.push((note, similarity));
             }
         }
-        
+
         // Sort by similarity score in descending order
         note_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        
+
         // Take the top max_notes
         note_scores.truncate(max_notes);
-        
-        // Return just the notes
-        note_scores.iter().map(|(note, _)| *note).collect()
+
+        note_scores
+            .iter()
+            .map(|&(note, _)| (note, self.harmonic_energy(spectrum, note)))
+            .collect()
+    }
+
+    /// Sums the spectral magnitude near `note`'s fundamental and its first
+    /// three harmonics, tolerating a little detuning around each bin.
+    fn harmonic_energy(&self, spectrum: &[f32], note: u8) -> f32 {
+        let bin_hz = self.sample_rate / (spectrum.len() * 2) as f32;
+        let fundamental = crate::utils::midi_note_to_freq(note);
+
+        let mut energy = 0.0;
+        for harmonic in 1..=4u32 {
+            let center_bin = ((fundamental * harmonic as f32) / bin_hz).round() as usize;
+            let lo = center_bin.saturating_sub(1);
+            let hi = center_bin + 1;
+            for bin in lo..=hi {
+                energy += spectrum.get(bin).copied().unwrap_or(0.0);
+            }
+        }
+        energy
     }
     
+    /// Monophonic pitch detection via the YIN algorithm, run directly on the
+    /// raw time-domain buffer. Unlike `detect_notes`, this needs no learned
+    /// spectra up front and returns at most a single note.
+    pub fn detect_note_yin(&self, samples: &[f32]) -> Option<(u8, f32)> {
+        const YIN_THRESHOLD: f32 = 0.12;
+
+        let max_tau = samples.len() / 2;
+        if max_tau < 2 {
+            return None;
+        }
+
+        // Difference function: d(tau) = sum_j (x[j] - x[j+tau])^2
+        let mut diff = vec![0.0f32; max_tau + 1];
+        for tau in 1..=max_tau {
+            let mut sum = 0.0;
+            for j in 0..(samples.len() - tau) {
+                let delta = samples[j] - samples[j + tau];
+                sum += delta * delta;
+            }
+            diff[tau] = sum;
+        }
+
+        // Cumulative mean normalized difference function.
+        let mut cmnd = vec![1.0f32; max_tau + 1];
+        let mut running_sum = 0.0;
+        for tau in 1..=max_tau {
+            running_sum += diff[tau];
+            cmnd[tau] = if running_sum > 0.0 {
+                diff[tau] * tau as f32 / running_sum
+            } else {
+                1.0
+            };
+        }
+
+        // Walk upward for the first dip below threshold that's a local minimum.
+        let mut tau_estimate = None;
+        for tau in 2..max_tau {
+            if cmnd[tau] < YIN_THRESHOLD && cmnd[tau] < cmnd[tau - 1] && cmnd[tau] < cmnd[tau + 1] {
+                tau_estimate = Some(tau);
+                break;
+            }
+        }
+        let tau = tau_estimate?;
+
+        // Parabolic interpolation around the estimated tau for sub-sample precision.
+        let s0 = cmnd[tau - 1];
+        let s1 = cmnd[tau];
+        let s2 = cmnd[tau + 1];
+        let denom = 2.0 * (2.0 * s1 - s2 - s0);
+        let tau_refined = if denom.abs() > f32::EPSILON {
+            tau as f32 + (s2 - s0) / denom
+        } else {
+            tau as f32
+        };
+
+        if tau_refined <= 0.0 {
+            return None;
+        }
+
+        let frequency = self.sample_rate / tau_refined;
+        Some((crate::utils::freq_to_midi_note(frequency), frequency))
+    }
+
     fn calculate_similarity(&self, spectrum: &[f32], learned_spectrum: &[f32]) -> f32 {
         // Simple cosine similarity implementation
         let mut dot_product = 0.0;
@@ -93,3 +179,53 @@ impl NoteDetector {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_wave(frequency: f32, sample_rate: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn yin_detects_a440() {
+        let mut detector = NoteDetector::new();
+        detector.initialize(44100.0);
+
+        let samples = sine_wave(440.0, 44100.0, 2048);
+        let (note, frequency) = detector.detect_note_yin(&samples).expect("should detect a pitch");
+
+        assert_eq!(note, 69); // A4
+        assert!((frequency - 440.0).abs() < 1.0, "frequency was {frequency}");
+    }
+
+    #[test]
+    fn yin_detects_low_e() {
+        let mut detector = NoteDetector::new();
+        detector.initialize(44100.0);
+
+        let samples = sine_wave(82.41, 44100.0, 4096); // Low E (E2)
+        let (note, frequency) = detector.detect_note_yin(&samples).expect("should detect a pitch");
+
+        assert_eq!(note, 40); // E2
+        assert!((frequency - 82.41).abs() < 1.0, "frequency was {frequency}");
+    }
+
+    #[test]
+    fn yin_returns_none_for_silence() {
+        let mut detector = NoteDetector::new();
+        detector.initialize(44100.0);
+
+        let samples = vec![0.0; 2048];
+        assert!(detector.detect_note_yin(&samples).is_none());
+    }
+
+    #[test]
+    fn yin_returns_none_for_too_short_buffer() {
+        let detector = NoteDetector::new();
+        assert!(detector.detect_note_yin(&[0.0, 0.0, 0.0]).is_none());
+    }
+}
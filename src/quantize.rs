@@ -0,0 +1,116 @@
+use nih_plug::prelude::*;
+
+/// Root-relative scale for snapping detected notes. `Chromatic` is a no-op
+/// passthrough; the others constrain notes to their scale degrees.
+#[derive(Enum, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    Pentatonic,
+}
+
+impl Scale {
+    /// 12-bit mask of allowed pitch classes relative to the root (bit N set
+    /// means the semitone N above the root is in the scale).
+    fn mask(self) -> u16 {
+        let intervals: &[u8] = match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Pentatonic => &[0, 2, 4, 7, 9],
+        };
+        intervals.iter().fold(0u16, |mask, &semitone| mask | (1 << semitone))
+    }
+}
+
+/// Snaps `note` to the nearest pitch class allowed by `root`/`scale`,
+/// preserving its octave. Ties resolve downward. No-op for `Scale::Chromatic`
+/// or when `note` is already in the scale.
+pub fn quantize_note(note: u8, root: u8, scale: Scale) -> u8 {
+    let mask = scale.mask();
+    let pitch_class = (note as i32 - root as i32).rem_euclid(12) as u8;
+
+    if mask & (1 << pitch_class) != 0 {
+        return note;
+    }
+
+    // Search outward from the pitch class for the nearest allowed degree,
+    // checking downward before upward so ties resolve down.
+    for distance in 1..=6i32 {
+        let down = (pitch_class as i32 - distance).rem_euclid(12) as u8;
+        if mask & (1 << down) != 0 {
+            return (note as i32 - distance).clamp(0, 127) as u8;
+        }
+        let up = (pitch_class as i32 + distance).rem_euclid(12) as u8;
+        if mask & (1 << up) != 0 {
+            return (note as i32 + distance).clamp(0, 127) as u8;
+        }
+    }
+
+    note
+}
+
+/// Quantizes a detected frequency by snapping its nearest MIDI note to the
+/// scale, then re-deriving a frequency that preserves the original's offset
+/// from that snapped note (so pitch bend still tracks correctly).
+pub fn quantize_frequency(frequency: f32, root: u8, scale: Scale) -> f32 {
+    let note = crate::utils::freq_to_midi_note(frequency);
+    let quantized_note = quantize_note(note, root, scale);
+
+    if quantized_note == note {
+        frequency
+    } else {
+        frequency * 2.0_f32.powf((quantized_note as f32 - note as f32) / 12.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chromatic_is_a_no_op() {
+        for note in 0..128 {
+            assert_eq!(quantize_note(note, 0, Scale::Chromatic), note);
+        }
+    }
+
+    #[test]
+    fn note_already_in_scale_is_unchanged() {
+        // C major: root C (60), note E (64) is scale degree 4, already in scale.
+        assert_eq!(quantize_note(64, 60, Scale::Major), 64);
+    }
+
+    #[test]
+    fn out_of_scale_note_snaps_to_nearest_degree() {
+        // C major: C# (61) is equidistant from C (60, -1) and D (62, +1);
+        // ties resolve downward.
+        assert_eq!(quantize_note(61, 60, Scale::Major), 60);
+    }
+
+    #[test]
+    fn out_of_scale_note_checks_downward_before_upward() {
+        // C pentatonic (C D E G A): F# (66) has no scale tone one semitone
+        // below (E is two away) but does one semitone above (G), so it
+        // snaps up even though the search always tries down first.
+        assert_eq!(quantize_note(66, 60, Scale::Pentatonic), 67);
+    }
+
+    #[test]
+    fn quantize_preserves_octave() {
+        // C#5 (73) should snap to C5 (72) in C major, not jump octaves.
+        assert_eq!(quantize_note(73, 60, Scale::Major), 72);
+    }
+
+    #[test]
+    fn quantize_frequency_matches_quantize_note() {
+        let a440 = 440.0; // A4, MIDI note 69
+        // In C minor, A (pitch class 9 relative to C) is out of scale;
+        // nearest allowed degree is G# (8), one semitone down.
+        let quantized = quantize_frequency(a440, 60, Scale::Minor);
+        let expected_note = quantize_note(69, 60, Scale::Minor);
+        assert_eq!(expected_note, 68);
+        assert!((quantized - crate::utils::midi_note_to_freq(68)).abs() < 0.01);
+    }
+}
@@ -1,5 +1,5 @@
 use nih_plug::prelude::*;
-use nih_plug_egui::{create_egui_editor, egui, EguiState};
+use nih_plug_egui::{create_egui_editor, egui, widgets::ParamSlider, EguiState};
 use std::sync::Arc;
 
 #[derive(Default)]
@@ -42,9 +42,45 @@ pub fn create_editor(params: Arc<dyn Params>) -> Option<Box<dyn Editor>> {
                             0.1..=1.0
                         ).text("Sensitivity"));
                         ui.add(egui::Slider::new(
-                            &mut setter.setter(&params.max_polyphony), 
+                            &mut setter.setter(&params.max_polyphony),
                             1..=12
                         ).text("Max Polyphony"));
+                        ui.add(egui::Slider::new(
+                            &mut setter.setter(&params.hop_size),
+                            256..=4096
+                        ).text("Hop Size").suffix(" samples"));
+
+                        ui.add(ParamSlider::for_param(&params.detection_mode, setter));
+                        ui.add(egui::Slider::new(
+                            &mut setter.setter(&params.pitch_bend_range),
+                            0.5..=12.0
+                        ).text("Pitch Bend Range").suffix(" st"));
+                    });
+
+                    ui.add_space(20.0);
+
+                    ui.vertical(|ui| {
+                        ui.heading("Quantize");
+                        ui.add(ParamSlider::for_param(&params.quantize_root, setter));
+                        ui.add(ParamSlider::for_param(&params.quantize_scale, setter));
+                    });
+
+                    ui.add_space(20.0);
+
+                    ui.vertical(|ui| {
+                        ui.heading("Velocity");
+                        ui.add(egui::Slider::new(
+                            &mut setter.setter(&params.velocity_floor_db),
+                            -80.0..=-20.0
+                        ).text("Floor").suffix(" dB"));
+                        ui.add(egui::Slider::new(
+                            &mut setter.setter(&params.velocity_ceiling_db),
+                            -20.0..=0.0
+                        ).text("Ceiling").suffix(" dB"));
+                        ui.add(egui::Slider::new(
+                            &mut setter.setter(&params.velocity_attack),
+                            0.01..=1.0
+                        ).text("Attack"));
                     });
                 });
                 
@@ -74,11 +110,28 @@ pub fn create_editor(params: Arc<dyn Params>) -> Option<Box<dyn Editor>> {
                         });
                     }
                 });
-                
+
                 ui.add_space(10.0);
                 ui.separator();
                 ui.add_space(10.0);
-                
+
+                // MIDI recording section
+                ui.collapsing("Record", |ui| {
+                    ui.checkbox(&mut setter.setter(&params.record_enabled), "Arm Recording");
+                    ui.add(egui::Slider::new(
+                        &mut setter.setter(&params.recording_bpm),
+                        40.0..=240.0
+                    ).text("Tempo").suffix(" BPM"));
+
+                    if ui.button("Save Recording").clicked() {
+                        setter.set_parameter(&params.save_recording, true);
+                    }
+                });
+
+                ui.add_space(10.0);
+                ui.separator();
+                ui.add_space(10.0);
+
                 // Visualization section - placeholder
                 ui.collapsing("Visualization", |ui| {
                     ui.checkbox(&mut setter.setter(&params.editor_state.as_ref().fft_visible), "Show FFT");
@@ -6,8 +6,13 @@ pub fn midi_note_to_freq(note: u8) -> f32 {
     440.0 * 2.0_f32.powf((note as f32 - 69.0) / 12.0)
 }
 
+pub fn freq_to_midi_note(freq: f32) -> u8 {
+    (69.0 + 12.0 * (freq / 440.0).log2()).round().clamp(0.0, 127.0) as u8
+}
+
+pub const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
 pub fn midi_note_to_name(note: u8) -> String {
-    const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
     let octave = note / 12 - 1;
     let note_name = NOTE_NAMES[(note % 12) as usize];
     format!("{}{}", note_name, octave)
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+/// A loud transient should still settle back down rather than permanently
+/// depress velocity for the rest of the session, so the running peak shrinks
+/// by this fraction of itself every call, never below the latest energy.
+const PEAK_DECAY: f32 = 0.0005;
+
+/// Converts raw per-note spectral (or time-domain) energy into a smoothed
+/// 0.0-1.0 MIDI velocity, tracked relative to a running peak so playing
+/// dynamics stay meaningful regardless of overall input level.
+pub struct VelocityTracker {
+    running_peak: f32,
+    smoothed: HashMap<u8, f32>,
+}
+
+impl VelocityTracker {
+    pub fn new() -> Self {
+        Self {
+            running_peak: 1e-6,
+            smoothed: HashMap::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.running_peak = 1e-6;
+        self.smoothed.clear();
+    }
+
+    /// Maps `energy` to a 0.0-1.0 velocity via a floor/ceiling dB range
+    /// relative to the running peak, then smooths it per-note with `attack`
+    /// (0.0 = no movement, 1.0 = track instantly) so sustained notes don't
+    /// jitter while fast transients still register a strong velocity.
+    pub fn velocity_for(&mut self, note: u8, energy: f32, floor_db: f32, ceiling_db: f32, attack: f32) -> f32 {
+        self.running_peak = (self.running_peak * (1.0 - PEAK_DECAY)).max(energy);
+
+        let db = 20.0 * (energy.max(1e-9) / self.running_peak).log10();
+        let target = ((db - floor_db) / (ceiling_db - floor_db)).clamp(0.0, 1.0);
+
+        let current = self.smoothed.entry(note).or_insert(target);
+        *current += (target - *current) * attack.clamp(0.0, 1.0);
+        *current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_note_velocity_equals_target_unsmoothed() {
+        let mut tracker = VelocityTracker::new();
+        // attack = 0.0 would normally pin an already-tracked note in place,
+        // but a brand-new note has nothing to smooth from yet and should
+        // still come out at the raw target.
+        let velocity = tracker.velocity_for(60, 0.5, -60.0, 0.0, 0.0);
+        assert!((velocity - 1.0).abs() < 1e-4, "velocity was {velocity}");
+    }
+
+    #[test]
+    fn running_peak_decays_instead_of_only_growing() {
+        let mut tracker = VelocityTracker::new();
+
+        // A loud transient sets the peak...
+        tracker.velocity_for(60, 1.0, -60.0, 0.0, 1.0);
+        // ...and a much quieter, sustained energy should climb back up as
+        // the peak decays toward it, rather than staying depressed forever.
+        let just_after_transient = tracker.velocity_for(60, 0.01, -60.0, 0.0, 1.0);
+        let mut velocity = just_after_transient;
+        for _ in 0..2000 {
+            velocity = tracker.velocity_for(60, 0.01, -60.0, 0.0, 1.0);
+        }
+
+        assert!(
+            velocity > just_after_transient,
+            "velocity should have recovered as the peak decayed: {just_after_transient} -> {velocity}"
+        );
+    }
+
+    #[test]
+    fn velocity_clamps_to_floor_and_ceiling() {
+        let mut tracker = VelocityTracker::new();
+        tracker.velocity_for(60, 1.0, -60.0, -20.0, 1.0); // establish the peak
+
+        let way_below_floor = tracker.velocity_for(61, 1e-9, -60.0, -20.0, 1.0);
+        assert!((way_below_floor - 0.0).abs() < 1e-4, "velocity was {way_below_floor}");
+
+        let at_peak = tracker.velocity_for(62, 1.0, -60.0, -20.0, 1.0);
+        assert!((at_peak - 1.0).abs() < 1e-4, "velocity was {at_peak}");
+    }
+}